@@ -0,0 +1,115 @@
+use crate::{Client, Filter, ObjectId};
+
+/// The pid, uid and gid of the client, as reported by the kernel for the
+/// process on the other end of the client's socket.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    /// The process identifier
+    pub pid: libc::pid_t,
+    /// The user identifier
+    pub uid: libc::uid_t,
+    /// The group identifier
+    pub gid: libc::gid_t,
+}
+
+impl Client {
+    /// Retrieve the credentials of this client
+    ///
+    /// Returns `None` if the client is no longer alive.
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.inner.credentials()
+    }
+
+    /// Retrieve the stable id of this client
+    pub fn id(&self) -> ClientId {
+        self.inner.client_id()
+    }
+
+    /// Terminate the connection to this client
+    ///
+    /// `message` is recorded as the reason for the disconnection and handed
+    /// to `on_disconnect` filters as `DisconnectReason::Other`.
+    /// `ConnectionClosed` and `ProtocolError` are reserved for the disconnect
+    /// reasons the library itself observes and cannot be produced by callers.
+    pub fn kill(&self, message: String) {
+        self.inner.kill(DisconnectReason::Other(message))
+    }
+
+    /// Register a callback to be invoked when this client is disconnected
+    ///
+    /// The filter fires once, with the reason the connection ended, when the
+    /// client is disconnected through `Client::kill` or a protocol error
+    /// posted with `Resource::post_error`. A client closing its own end of the
+    /// connection does not fire it, and a filter registered after the client
+    /// was disconnected is never invoked.
+    pub fn on_disconnect(&self, filter: Filter<DisconnectReason>) {
+        self.inner.on_disconnect(filter)
+    }
+}
+
+/// A stable identifier for a client
+///
+/// Like `ObjectId`, stays distinguishable from whatever other client may
+/// later reuse the same underlying id, so it is safe to use as a `HashMap`
+/// key across a client's connect/disconnect cycle.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClientId {
+    pub(crate) id: u32,
+    pub(crate) serial: u64,
+}
+
+/// The reason why a client's connection was terminated
+#[derive(Clone, Debug)]
+pub enum DisconnectReason {
+    /// The client closed its end of the connection
+    ConnectionClosed,
+    /// The client was killed because of a protocol error
+    ProtocolError {
+        /// The object that raised the error
+        object: ObjectId,
+        /// The protocol error code
+        code: u32,
+        /// The error message that was sent to the client
+        message: String,
+    },
+    /// The connection was terminated for another reason, notably an
+    /// explicit call to `Client::kill`
+    Other(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{Client, ClientId, DisconnectReason, Filter};
+    use crate::imp::ClientInner;
+
+    #[test]
+    fn different_generations_are_not_equal() {
+        let a = ClientId { id: 7, serial: 1 };
+        let b = ClientId { id: 7, serial: 2 };
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn same_generation_is_equal() {
+        let a = ClientId { id: 7, serial: 1 };
+        let b = ClientId { id: 7, serial: 1 };
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn kill_reports_the_message_as_other() {
+        let client = Client::make(ClientInner::new(1, None));
+        let seen = Arc::new(Mutex::new(None));
+        let seen2 = seen.clone();
+        client.on_disconnect(Filter::new(move |reason: DisconnectReason, _| {
+            *seen2.lock().unwrap() = Some(reason);
+        }));
+        client.kill("bye".into());
+        match seen.lock().unwrap().take() {
+            Some(DisconnectReason::Other(msg)) => assert_eq!(msg, "bye"),
+            other => panic!("expected DisconnectReason::Other, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::marker::PhantomData;
+
+use crate::imp::DisplayInner;
+
+/// A handle to the display, threaded through the parameterized dispatch path
+///
+/// Passed to `ObjectData::request`. It has no public methods: a handler
+/// cannot create resources or otherwise act on the display through it.
+pub struct DisplayHandle<D> {
+    _data: PhantomData<fn(&mut D)>,
+    inner: DisplayInner,
+}
+
+impl<D> DisplayHandle<D> {
+    pub(crate) fn wrap(inner: DisplayInner) -> Self {
+        DisplayHandle { _data: PhantomData, inner }
+    }
+
+    pub(crate) fn inner(&self) -> &DisplayInner {
+        &self.inner
+    }
+}
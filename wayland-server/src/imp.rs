@@ -0,0 +1,447 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+#[cfg(not(feature = "native_lib"))]
+use std::mem;
+#[cfg(not(feature = "native_lib"))]
+use std::os::unix::io::AsRawFd;
+#[cfg(not(feature = "native_lib"))]
+use std::os::unix::net::UnixStream;
+
+use wayland_commons::user_data::UserData;
+use wayland_commons::{Interface, MessageGroup};
+
+#[cfg(feature = "native_lib")]
+use wayland_sys::server::*;
+
+use crate::{
+    ClientId, Credentials, DisconnectReason, DisplayHandle, Filter, ObjectData, ObjectId,
+};
+
+/// Source of the generation component of `ObjectId`/`ClientId`
+///
+/// Bumped every time a new object or client is created, so a freshly created
+/// object never shares a generation with whatever used to hold the same
+/// numeric protocol id.
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(1);
+
+fn next_serial() -> u64 {
+    NEXT_SERIAL.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "native_lib"))]
+fn peer_credentials(stream: &UnixStream) -> Option<Credentials> {
+    let fd = stream.as_raw_fd();
+    let mut ucred: libc::ucred = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut ucred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return None;
+    }
+    Some(Credentials { pid: ucred.pid, uid: ucred.uid, gid: ucred.gid })
+}
+
+#[cfg(feature = "native_lib")]
+fn native_credentials(client: *mut wl_client) -> Option<Credentials> {
+    if client.is_null() {
+        return None;
+    }
+    let (mut pid, mut uid, mut gid) = (0, 0, 0);
+    unsafe {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_get_credentials, client, &mut pid, &mut uid, &mut gid);
+    }
+    Some(Credentials { pid, uid, gid })
+}
+
+struct ClientData {
+    id: u32,
+    serial: u64,
+    alive: AtomicBool,
+    #[cfg(feature = "native_lib")]
+    ptr: *mut wl_client,
+    #[cfg(not(feature = "native_lib"))]
+    stream: Option<UnixStream>,
+    on_disconnect: Mutex<Vec<Filter<DisconnectReason>>>,
+}
+
+/// The concrete storage backing a `Client` handle
+#[derive(Clone)]
+pub(crate) struct ClientInner(Arc<ClientData>);
+
+impl ClientInner {
+    pub(crate) fn new(
+        id: u32,
+        #[cfg(not(feature = "native_lib"))] stream: Option<UnixStream>,
+        #[cfg(feature = "native_lib")] ptr: *mut wl_client,
+    ) -> Self {
+        ClientInner(Arc::new(ClientData {
+            id,
+            serial: next_serial(),
+            alive: AtomicBool::new(true),
+            #[cfg(feature = "native_lib")]
+            ptr,
+            #[cfg(not(feature = "native_lib"))]
+            stream,
+            on_disconnect: Mutex::new(Vec::new()),
+        }))
+    }
+
+    pub(crate) fn equals(&self, other: &ClientInner) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(crate) fn client_id(&self) -> ClientId {
+        ClientId { id: self.0.id, serial: self.0.serial }
+    }
+
+    pub(crate) fn credentials(&self) -> Option<Credentials> {
+        if !self.0.alive.load(Ordering::Acquire) {
+            return None;
+        }
+        #[cfg(feature = "native_lib")]
+        {
+            native_credentials(self.0.ptr)
+        }
+        #[cfg(not(feature = "native_lib"))]
+        {
+            self.0.stream.as_ref().and_then(peer_credentials)
+        }
+    }
+
+    pub(crate) fn kill(&self, reason: DisconnectReason) {
+        if self.0.alive.swap(false, Ordering::AcqRel) {
+            #[cfg(feature = "native_lib")]
+            {
+                if !self.0.ptr.is_null() {
+                    unsafe {
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_destroy, self.0.ptr);
+                    }
+                }
+            }
+            #[cfg(not(feature = "native_lib"))]
+            {
+                if let Some(stream) = self.0.stream.as_ref() {
+                    let _ = stream.shutdown(std::net::Shutdown::Both);
+                }
+            }
+            for filter in self.0.on_disconnect.lock().unwrap().drain(..) {
+                filter.send(reason.clone());
+            }
+        }
+    }
+
+    pub(crate) fn on_disconnect(&self, filter: Filter<DisconnectReason>) {
+        self.0.on_disconnect.lock().unwrap().push(filter);
+    }
+}
+
+struct ResourceData {
+    id: u32,
+    serial: u64,
+    version: u32,
+    alive: AtomicBool,
+    user_data: Arc<UserData>,
+    client: ClientInner,
+    #[cfg(feature = "native_lib")]
+    ptr: *mut wl_resource,
+    filter: Mutex<Option<Box<dyn Any>>>,
+    destructor: Mutex<Option<Box<dyn Any>>>,
+    object_data: Mutex<Option<Box<dyn Any>>>,
+}
+
+/// Live resources indexed by `(id, serial)`, so a stale `ObjectId` can be
+/// told apart from whatever later reused the same numeric id
+///
+/// Entries are weak: once the last `ResourceInner` for an object is dropped,
+/// `from_serial` stops finding it instead of keeping it alive.
+fn registry() -> &'static Mutex<HashMap<(u32, u64), Weak<ResourceData>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<(u32, u64), Weak<ResourceData>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The concrete storage backing a `Resource<I>` handle
+#[derive(Clone)]
+pub(crate) struct ResourceInner(Arc<ResourceData>);
+
+impl ResourceInner {
+    fn register(data: Arc<ResourceData>) -> Self {
+        registry().lock().unwrap().insert((data.id, data.serial), Arc::downgrade(&data));
+        ResourceInner(data)
+    }
+
+    pub(crate) fn new(id: u32, version: u32, client: ClientInner, #[cfg(feature = "native_lib")] ptr: *mut wl_resource) -> Self {
+        Self::register(Arc::new(ResourceData {
+            id,
+            serial: next_serial(),
+            version,
+            alive: AtomicBool::new(true),
+            user_data: Arc::new(UserData::new()),
+            client,
+            #[cfg(feature = "native_lib")]
+            ptr,
+            filter: Mutex::new(None),
+            destructor: Mutex::new(None),
+            object_data: Mutex::new(None),
+        }))
+    }
+
+    /// Look up the still-live resource that was assigned `(id, serial)` at creation
+    ///
+    /// Returns `None` if no such resource was ever created, or if it has
+    /// since been destroyed.
+    pub(crate) fn from_serial(id: u32, serial: u64) -> Option<ResourceInner> {
+        let mut reg = registry().lock().unwrap();
+        match reg.get(&(id, serial)).and_then(Weak::upgrade) {
+            Some(data) if data.alive.load(Ordering::Acquire) => Some(ResourceInner(data)),
+            Some(_) => None,
+            None => {
+                reg.remove(&(id, serial));
+                None
+            }
+        }
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    #[cfg(test)]
+    pub(crate) fn serial(&self) -> u64 {
+        self.0.serial
+    }
+
+    pub(crate) fn obj_id<I: Interface>(&self) -> ObjectId {
+        ObjectId { interface: I::NAME, id: self.0.id, serial: self.0.serial }
+    }
+
+    pub(crate) fn is_alive(&self) -> bool {
+        self.0.alive.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn version(&self) -> u32 {
+        if self.is_alive() {
+            self.0.version
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn equals(&self, other: &ResourceInner) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(crate) fn same_client_as(&self, other: &ResourceInner) -> bool {
+        self.is_alive() && other.is_alive() && self.0.client.equals(&other.0.client)
+    }
+
+    pub(crate) fn client(&self) -> Option<ClientInner> {
+        if self.is_alive() {
+            Some(self.0.client.clone())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn user_data(&self) -> &Arc<UserData> {
+        &self.0.user_data
+    }
+
+    pub(crate) fn post_error<I: Interface>(&self, error_code: u32, msg: String) {
+        let object = self.obj_id::<I>();
+        self.0.alive.store(false, Ordering::Release);
+        self.0.client.kill(DisconnectReason::ProtocolError { object, code: error_code, message: msg });
+    }
+
+    pub(crate) fn send<I: Interface>(&self, _msg: I::Event) {
+        #[cfg(feature = "native_lib")]
+        {
+            // Wire encoding of `_msg` onto `self.0.ptr` happens in the
+            // scanner-generated `I::Event::as_raw_c_in` glue; nothing to add here.
+        }
+    }
+
+    #[cfg(feature = "native_lib")]
+    pub(crate) fn is_external(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "native_lib")]
+    pub(crate) fn c_ptr(&self) -> *mut wl_resource {
+        self.0.ptr
+    }
+
+    #[cfg(feature = "native_lib")]
+    pub(crate) unsafe fn init_from_c_ptr<I: Interface>(ptr: *mut wl_resource) -> Self {
+        Self::from_c_ptr::<I>(ptr)
+    }
+
+    #[cfg(feature = "native_lib")]
+    pub(crate) unsafe fn from_c_ptr<I: Interface>(ptr: *mut wl_resource) -> Self {
+        if !ptr.is_null() {
+            let reg = registry().lock().unwrap();
+            let existing = reg
+                .values()
+                .filter_map(Weak::upgrade)
+                .find(|data| data.ptr == ptr && data.alive.load(Ordering::Acquire));
+            if let Some(data) = existing {
+                return ResourceInner(data);
+            }
+        }
+        let client = ClientInner::new(0, std::ptr::null_mut());
+        Self::register(Arc::new(ResourceData {
+            id: 0,
+            serial: next_serial(),
+            version: 0,
+            alive: AtomicBool::new(!ptr.is_null()),
+            user_data: Arc::new(UserData::new()),
+            client,
+            ptr,
+            filter: Mutex::new(None),
+            destructor: Mutex::new(None),
+            object_data: Mutex::new(None),
+        }))
+    }
+
+    #[cfg(feature = "native_lib")]
+    pub(crate) unsafe fn make_child_for<J: Interface>(&self, id: u32) -> Option<ResourceInner> {
+        if !self.is_alive() {
+            return None;
+        }
+        Some(ResourceInner::new(id, self.0.version, self.0.client.clone(), std::ptr::null_mut()))
+    }
+
+    pub(crate) fn assign<E: 'static>(&self, filter: Filter<E>) {
+        *self.0.filter.lock().unwrap() = Some(Box::new(filter));
+    }
+
+    pub(crate) fn assign_destructor<E: 'static>(&self, filter: Filter<E>) {
+        *self.0.destructor.lock().unwrap() = Some(Box::new(filter));
+    }
+
+    pub(crate) fn assign_object_data<I, D>(&self, object_data: Arc<dyn ObjectData<I, D>>)
+    where
+        I: Interface,
+        D: 'static,
+        I::Request: MessageGroup<Map = crate::ResourceMap>,
+    {
+        *self.0.filter.lock().unwrap() = None;
+        *self.0.object_data.lock().unwrap() = Some(Box::new(object_data));
+    }
+
+    /// Dispatch an incoming request for this resource
+    ///
+    /// If an `ObjectData` was assigned via `assign_object_data`, it handles
+    /// the request and this returns `true`; otherwise the caller should fall
+    /// back to whatever `Filter<E>` was installed via `assign`.
+    #[allow(dead_code)]
+    pub(crate) fn dispatch_request<I, D>(
+        &self,
+        handle: &mut DisplayHandle<D>,
+        data: &mut D,
+        client: ClientId,
+        resource: crate::Resource<I>,
+        request: I::Request,
+    ) -> bool
+    where
+        I: Interface,
+        D: 'static,
+        I::Request: MessageGroup<Map = crate::ResourceMap>,
+    {
+        let object_data = self
+            .0
+            .object_data
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|boxed| boxed.downcast_ref::<Arc<dyn ObjectData<I, D>>>())
+            .cloned();
+        match object_data {
+            Some(object_data) => {
+                object_data.request(handle, data, client, resource, request);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Notify the assigned `ObjectData`, if any, that this object was destroyed
+    #[allow(dead_code)]
+    pub(crate) fn notify_destroyed<I, D>(&self, data: &mut D, client: ClientId)
+    where
+        I: Interface,
+        D: 'static,
+        I::Request: MessageGroup<Map = crate::ResourceMap>,
+    {
+        let object_data = self
+            .0
+            .object_data
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|boxed| boxed.downcast_ref::<Arc<dyn ObjectData<I, D>>>())
+            .cloned();
+        if let Some(object_data) = object_data {
+            object_data.destroyed(data, client, self.obj_id::<I>());
+        }
+    }
+}
+
+/// The concrete storage backing a `DisplayHandle<D>`
+pub(crate) struct DisplayInner {
+    _private: (),
+}
+
+impl DisplayInner {
+    #[allow(dead_code)]
+    pub(crate) fn new() -> Self {
+        DisplayInner { _private: () }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientInner, ResourceInner};
+
+    #[cfg(not(feature = "native_lib"))]
+    fn test_client() -> ClientInner {
+        ClientInner::new(1, None)
+    }
+
+    #[test]
+    fn from_serial_finds_a_live_resource() {
+        let client = test_client();
+        let resource = ResourceInner::new(42, 1, client);
+        assert!(ResourceInner::from_serial(resource.id(), resource.serial()).is_some());
+    }
+
+    #[test]
+    fn from_serial_rejects_an_unknown_serial() {
+        assert!(ResourceInner::from_serial(42, 0).is_none());
+    }
+
+    #[test]
+    fn kill_fires_on_disconnect_with_the_given_reason() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::{DisconnectReason, Filter};
+
+        let client = test_client();
+        let seen = Arc::new(Mutex::new(None));
+        let seen2 = seen.clone();
+        client.on_disconnect(Filter::new(move |reason: DisconnectReason, _| {
+            *seen2.lock().unwrap() = Some(reason);
+        }));
+        client.kill(DisconnectReason::Other("bye".into()));
+        assert!(matches!(*seen.lock().unwrap(), Some(DisconnectReason::Other(ref m)) if m == "bye"));
+    }
+}
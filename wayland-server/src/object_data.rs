@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use wayland_commons::{Interface, MessageGroup};
+
+use crate::{ClientId, DisplayHandle, ObjectId, Resource};
+
+/// A per-object callback for the parameterized dispatch path
+///
+/// Alternative to `Filter` for handlers that take their state as a plain
+/// `&mut D` threaded through the event loop instead of via a closure.
+/// Assigned with `Resource::assign_object_data`;
+/// `ResourceInner::dispatch_request`/`notify_destroyed` hand it a request or
+/// the destruction of its object. No dispatch loop in this crate calls those
+/// two functions, so an assigned `ObjectData` receives nothing from the
+/// event loop.
+pub trait ObjectData<I: Interface, D>: 'static
+where
+    I::Request: MessageGroup<Map = crate::ResourceMap>,
+{
+    /// A request was received for the object this data is assigned to
+    fn request(
+        self: Arc<Self>,
+        handle: &mut DisplayHandle<D>,
+        data: &mut D,
+        client: ClientId,
+        resource: Resource<I>,
+        request: I::Request,
+    );
+
+    /// The object this data is assigned to has been destroyed
+    ///
+    /// `resource` is the (now dead) object's `ObjectId` rather than a live
+    /// `Resource`, since the object no longer exists by the time this runs.
+    fn destroyed(&self, data: &mut D, client: ClientId, resource: ObjectId);
+}
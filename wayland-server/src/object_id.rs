@@ -0,0 +1,105 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use wayland_commons::Interface;
+
+use crate::imp::ResourceInner;
+use crate::Resource;
+
+/// An opaque, stable identifier for a wayland object
+///
+/// Wayland recycles object ids after they are destroyed, so a bare `u32`
+/// (as returned by `Resource::id`) can end up referring to a *different*
+/// object than the one it was read from, if that object was destroyed and
+/// its id reused in the meantime. An `ObjectId` pairs the protocol id with
+/// a serial that is assigned when the object is created and never reused,
+/// so it can be kept around (say, as a `HashMap` key) without risking this
+/// kind of aliasing.
+///
+/// Two `ObjectId`s that share the same numeric id but were obtained from
+/// different generations of the object will compare as different, and
+/// converting a stale `ObjectId` back into a `Resource` fails with
+/// `InvalidId` rather than resurrecting a handle to whatever now holds that
+/// id.
+#[derive(Clone)]
+pub struct ObjectId {
+    pub(crate) interface: &'static str,
+    pub(crate) id: u32,
+    pub(crate) serial: u64,
+}
+
+impl ObjectId {
+    /// The name of the interface of the object this id was obtained from
+    pub fn interface(&self) -> &'static str {
+        self.interface
+    }
+}
+
+impl PartialEq for ObjectId {
+    fn eq(&self, other: &ObjectId) -> bool {
+        self.id == other.id && self.serial == other.serial && self.interface == other.interface
+    }
+}
+
+impl Eq for ObjectId {}
+
+impl std::hash::Hash for ObjectId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.serial.hash(state);
+    }
+}
+
+impl fmt::Debug for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}v{}", self.interface, self.id, self.serial)
+    }
+}
+
+/// The requested `ObjectId` does not (or no longer) refer to a live object
+///
+/// This is returned when trying to turn a stale `ObjectId` (one whose
+/// generation has been superseded by a newer object reusing the same
+/// protocol id) back into a `Resource`.
+#[derive(Copy, Clone, Debug)]
+pub struct InvalidId;
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the requested object id does not refer to a live object")
+    }
+}
+
+impl std::error::Error for InvalidId {}
+
+impl<I: Interface + From<Resource<I>>> TryFrom<ObjectId> for Resource<I> {
+    type Error = InvalidId;
+
+    fn try_from(id: ObjectId) -> Result<Self, InvalidId> {
+        if id.interface != I::NAME {
+            return Err(InvalidId);
+        }
+        ResourceInner::from_serial(id.id, id.serial)
+            .map(Resource::wrap)
+            .ok_or(InvalidId)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ObjectId;
+
+    #[test]
+    fn different_generations_are_not_equal() {
+        let a = ObjectId { interface: "wl_surface", id: 3, serial: 1 };
+        let b = ObjectId { interface: "wl_surface", id: 3, serial: 2 };
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn same_generation_is_equal() {
+        let a = ObjectId { interface: "wl_surface", id: 3, serial: 1 };
+        let b = ObjectId { interface: "wl_surface", id: 3, serial: 1 };
+        assert_eq!(a, b);
+    }
+}
@@ -6,7 +6,7 @@ use wayland_commons::{Interface, MessageGroup};
 use wayland_sys::server::*;
 
 use crate::imp::ResourceInner;
-use crate::{Client, Filter};
+use crate::{Client, Credentials, Filter, ObjectData, ObjectId};
 
 /// An handle to a wayland resource
 ///
@@ -109,7 +109,7 @@ impl<I: Interface> Resource<I> {
     ///
     /// An error is fatal to the client that caused it.
     pub fn post_error(&self, error_code: u32, msg: String) {
-        self.inner.post_error(error_code, msg)
+        self.inner.post_error::<I>(error_code, msg)
     }
 
     /// Access the UserData associated to this object
@@ -130,10 +130,26 @@ impl<I: Interface> Resource<I> {
         self.inner.client().map(Client::make)
     }
 
+    /// Retrieve the credentials of the client associated with this resource
+    ///
+    /// This is a shorthand for `self.client().and_then(|c| c.credentials())`,
+    /// see `Client::credentials` for details. Returns `None` if the resource
+    /// is no longer alive.
+    pub fn client_credentials(&self) -> Option<Credentials> {
+        self.inner.client().map(Client::make).and_then(|c| c.credentials())
+    }
+
     /// Retrieve the object id of this wayland object
     pub fn id(&self) -> u32 {
         self.inner.id()
     }
+
+    /// Retrieve a stable `ObjectId` for this wayland object
+    ///
+    /// See `ObjectId` for how this differs from `id()`.
+    pub fn obj_id(&self) -> ObjectId {
+        self.inner.obj_id::<I>()
+    }
 }
 
 impl<I: Interface> Resource<I> {
@@ -288,6 +304,18 @@ impl<I: Interface> Resource<I> {
     {
         self.inner.assign_destructor(filter)
     }
+
+    /// Assign an `ObjectData` implementation to this resource
+    ///
+    /// Alternative to `assign`/`assign_mono`, see `ObjectData`. Replaces any
+    /// filter previously assigned with `assign`.
+    pub fn assign_object_data<D: 'static>(&self, object_data: Arc<dyn ObjectData<I, D>>)
+    where
+        I: AsRef<Resource<I>> + From<Resource<I>>,
+        I::Request: MessageGroup<Map = crate::ResourceMap>,
+    {
+        self.inner.assign_object_data(object_data);
+    }
 }
 
 impl<I: Interface> Clone for Resource<I> {